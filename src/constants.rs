@@ -37,4 +37,23 @@ pub mod header {
     /// Contents of the link if the file is either a symlink or a hard
     /// link. Otherwise empty.
     pub const LINK_NAME_RANGE: Range<usize> = 157..257;
+    /// Magic value identifying a USTAR (or derived) header.
+    ///
+    /// Set to `"ustar\0"` when the header uses the fields below.
+    pub const MAGIC_RANGE: Range<usize> = 257..263;
+    /// USTAR version, normally `"00"`.
+    pub const VERSION_RANGE: Range<usize> = 263..265;
+    /// Name of the file owner.
+    pub const UNAME_RANGE: Range<usize> = 265..297;
+    /// Name of the file's group.
+    pub const GNAME_RANGE: Range<usize> = 297..329;
+    /// Major number for character or block special files.
+    pub const DEVMAJOR_RANGE: Range<usize> = 329..337;
+    /// Minor number for character or block special files.
+    pub const DEVMINOR_RANGE: Range<usize> = 337..345;
+    /// Prefix prepended to `NAME_RANGE` to form paths longer than 100 bytes.
+    pub const PREFIX_RANGE: Range<usize> = 345..500;
+
+    /// Magic bytes indicating a USTAR header.
+    pub const USTAR_MAGIC: &[u8] = b"ustar\0";
 }
@@ -2,22 +2,28 @@
 //!
 //! This crate contains classes and methods to efficiently read tar files.
 use std::{fmt, io};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::error::Error;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::io::{Read, Seek, SeekFrom};
 use std::num::ParseIntError;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Component, Path, PathBuf};
 
+use utils::parse_numeric;
 use utils::parse_octal;
 use utils::parse_size;
 
 use crate::constants::{BLOCK_SIZE, TarBlock};
 use crate::constants::header::{
-    CHECKSUM_RANGE, GROUP_RANGE, LINK_NAME_RANGE, LINK_TYPE_OFFSET, MODE_RANGE, MTIME_RANGE,
-    NAME_RANGE, OWNER_RANGE, SIZE_RANGE,
+    CHECKSUM_RANGE, GNAME_RANGE, GROUP_RANGE, LINK_NAME_RANGE, LINK_TYPE_OFFSET, MAGIC_RANGE,
+    MODE_RANGE, MTIME_RANGE, NAME_RANGE, OWNER_RANGE, PREFIX_RANGE, SIZE_RANGE, UNAME_RANGE,
+    USTAR_MAGIC,
 };
-use crate::utils::{compute_checksum, trimmed_osstr};
+use crate::utils::{compute_checksum, parse_pax_records, trimmed_osstr};
 
 pub mod constants;
 mod utils;
@@ -51,6 +57,20 @@ pub enum TarError {
     ParseError(ParseIntError),
     IOError(io::Error),
     FileEnd,
+    /// An entry's name, or a link entry's target, would escape the
+    /// extraction root passed to [`TarHeader::safe_path`].
+    UnsafePath,
+    /// A numeric header field decoded to a value that doesn't fit in the
+    /// target type (e.g. a base-256 field encoding a negative number into
+    /// an unsigned field).
+    FieldOutOfRange,
+    /// A header's `size` field claims a payload larger than this crate is
+    /// willing to allocate for it (see [`MAX_LONG_NAME_SIZE`] and
+    /// [`MAX_PAX_HEADER_SIZE`]).
+    PayloadTooLarge,
+    /// An entry's `size` implies a block count that overflows `usize`
+    /// arithmetic when rounded back up to a byte count.
+    SizeOverflow,
 }
 
 impl PartialEq for TarError {
@@ -64,6 +84,10 @@ impl PartialEq for TarError {
             (ParseError(_), ParseError(_)) => true,
             (IOError(_), IOError(_)) => true,
             (FileEnd, FileEnd) => true,
+            (UnsafePath, UnsafePath) => true,
+            (FieldOutOfRange, FieldOutOfRange) => true,
+            (PayloadTooLarge, PayloadTooLarge) => true,
+            (SizeOverflow, SizeOverflow) => true,
             _ => false,
         }
     }
@@ -98,14 +122,18 @@ impl From<io::Error> for TarError {
 
 #[derive(Debug)]
 pub struct TarHeader<'a> {
-    pub name: &'a OsStr,
+    pub name: Cow<'a, OsStr>,
     pub mode: u32,
     pub owner: u32,
     pub group: u32,
     pub size: usize,
     pub mtime: u64,
     pub link: LinkType,
-    pub link_name: Option<&'a OsStr>,
+    pub link_name: Option<Cow<'a, OsStr>>,
+    /// Name of the file owner. Only present in USTAR (and derived) headers.
+    pub owner_name: Option<Cow<'a, OsStr>>,
+    /// Name of the file's group. Only present in USTAR (and derived) headers.
+    pub group_name: Option<Cow<'a, OsStr>>,
 }
 
 impl<'a> TarHeader<'a> {
@@ -130,21 +158,61 @@ impl<'a> TarHeader<'a> {
 
     pub fn from_v7_header(block: &TarBlock) -> Result<TarHeader, TarError> {
         Ok(TarHeader {
-            name: trimmed_osstr(&block[NAME_RANGE])
-                .ok_or(TarError::EmptyName)?,
+            name: Cow::Borrowed(trimmed_osstr(&block[NAME_RANGE])
+                .ok_or(TarError::EmptyName)?),
             mode: parse_octal(&block[MODE_RANGE])?,
             owner: parse_octal(&block[OWNER_RANGE])?,
             group: parse_octal(&block[GROUP_RANGE])?,
             size: parse_size(&block[SIZE_RANGE])?,
-            mtime: parse_octal(&block[MTIME_RANGE])?,
+            mtime: parse_numeric(&block[MTIME_RANGE])?,
             link: block[LINK_TYPE_OFFSET].into(),
-            link_name: trimmed_osstr(&block[LINK_NAME_RANGE]),
+            link_name: trimmed_osstr(&block[LINK_NAME_RANGE]).map(Cow::Borrowed),
+            owner_name: None,
+            group_name: None,
         })
     }
 
+    /// Parse a USTAR header, reconstructing names longer than 100 bytes from
+    /// the `prefix` field and exposing the owner/group names.
+    pub fn from_ustar_header(block: &TarBlock) -> Result<TarHeader, TarError> {
+        let name = trimmed_osstr(&block[NAME_RANGE]).ok_or(TarError::EmptyName)?;
+        let prefix = trimmed_osstr(&block[PREFIX_RANGE]);
+
+        let name = match prefix {
+            Some(prefix) => {
+                let mut joined = OsString::with_capacity(prefix.len() + 1 + name.len());
+                joined.push(prefix);
+                joined.push("/");
+                joined.push(name);
+                Cow::Owned(joined)
+            }
+            None => Cow::Borrowed(name),
+        };
+
+        Ok(TarHeader {
+            name,
+            mode: parse_octal(&block[MODE_RANGE])?,
+            owner: parse_octal(&block[OWNER_RANGE])?,
+            group: parse_octal(&block[GROUP_RANGE])?,
+            size: parse_size(&block[SIZE_RANGE])?,
+            mtime: parse_numeric(&block[MTIME_RANGE])?,
+            link: block[LINK_TYPE_OFFSET].into(),
+            link_name: trimmed_osstr(&block[LINK_NAME_RANGE]).map(Cow::Borrowed),
+            owner_name: trimmed_osstr(&block[UNAME_RANGE]).map(Cow::Borrowed),
+            group_name: trimmed_osstr(&block[GNAME_RANGE]).map(Cow::Borrowed),
+        })
+    }
+
+    /// Check whether a header block carries the `"ustar\0"` magic.
+    fn is_ustar(block: &TarBlock) -> bool {
+        &block[MAGIC_RANGE] == USTAR_MAGIC
+    }
+
     pub fn from_block(block: &TarBlock) -> Result<TarHeader, TarError> {
         if !Self::validate_checksum(block) {
             Err(TarError::CheckSum)
+        } else if Self::is_ustar(block) {
+            Self::from_ustar_header(block)
         } else {
             Self::from_v7_header(block)
         }
@@ -158,6 +226,112 @@ impl<'a> TarHeader<'a> {
             size
         }
     }
+
+    /// Resolve this entry's name to a path under `root`, rejecting absolute
+    /// paths, `..` components, and anything else that would let the entry
+    /// escape `root` — the standard defense against tar directory-traversal
+    /// ("tarbomb") attacks. For hard links and symlinks, the link target is
+    /// checked the same way, resolved relative to the directory containing
+    /// this entry (as real symlink targets are), not relative to `root`.
+    ///
+    /// This also walks the real filesystem under `root`, so that a symlink
+    /// planted by an *earlier* entry in the same archive (e.g. `foo ->
+    /// /etc`, followed by an innocent-looking `foo/passwd`) is caught even
+    /// though neither name alone contains a `..` component. Callers should
+    /// still extract through this resolved path rather than following
+    /// symlinks themselves.
+    pub fn safe_path(&self, root: impl AsRef<Path>) -> Result<PathBuf, TarError> {
+        let root = root.as_ref();
+        let path = sanitize_path(root, &self.name)?;
+        verify_against_filesystem(root, &path)?;
+
+        if let LinkType::Hard | LinkType::Symbolic = self.link {
+            if let Some(link_name) = &self.link_name {
+                let name_dir = Path::new(&self.name).parent().unwrap_or_else(|| Path::new(""));
+                let (base, depth) = resolve_relative(root.to_path_buf(), 0, name_dir.as_os_str())?;
+                let (link_path, _) = resolve_relative(base, depth, link_name)?;
+                verify_against_filesystem(root, &link_path)?;
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+/// Join `name` onto `root`, rejecting absolute paths and any path that
+/// contains a `..` component.
+fn sanitize_path(root: &Path, name: &OsStr) -> Result<PathBuf, TarError> {
+    if Path::new(name).components().any(|c| c == Component::ParentDir) {
+        return Err(TarError::UnsafePath);
+    }
+    resolve_relative(root.to_path_buf(), 0, name).map(|(path, _)| path)
+}
+
+/// Resolve `name`'s components onto `base`, which is already `depth` levels
+/// below `root`, rejecting anything that would climb back above `root` via
+/// `..` components. Returns the resolved path along with its depth below
+/// `root`, so callers can chain a further relative resolution onto it (as
+/// `safe_path` does to resolve a symlink target relative to its entry's
+/// containing directory).
+fn resolve_relative(mut base: PathBuf, mut depth: usize, name: &OsStr) -> Result<(PathBuf, usize), TarError> {
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => {
+                base.push(part);
+                depth += 1;
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                depth = depth.checked_sub(1).ok_or(TarError::UnsafePath)?;
+                base.pop();
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(TarError::UnsafePath),
+        }
+    }
+
+    Ok((base, depth))
+}
+
+/// Walk `path`'s components under `root`, confirming that every prefix that
+/// already exists on disk still resolves to somewhere under `root` once
+/// symlinks are followed.
+///
+/// `sanitize_path` only reasons about the entry's name lexically, so it
+/// can't see that a *previous* entry already extracted in this archive
+/// redirected one of `path`'s intermediate components elsewhere via a
+/// symlink. If `root` itself, or some component of `path`, doesn't exist
+/// yet, there's nothing on disk that could redirect us, so this is a
+/// no-op; any other error resolving a component (e.g. a symlink loop)
+/// fails the check rather than silently treating it as safe.
+fn verify_against_filesystem(root: &Path, path: &Path) -> Result<(), TarError> {
+    let canonical_root = match root.canonicalize() {
+        Ok(canonical_root) => canonical_root,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(TarError::IOError(e)),
+    };
+
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative,
+        Err(_) => return Ok(()),
+    };
+
+    let mut current = canonical_root.clone();
+    for component in relative.components() {
+        current.push(component);
+
+        current = match current.canonicalize() {
+            Ok(resolved) => resolved,
+            // Doesn't exist on disk yet; nothing further down can either.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(TarError::IOError(e)),
+        };
+
+        if !current.starts_with(&canonical_root) {
+            return Err(TarError::UnsafePath);
+        }
+    }
+
+    Ok(())
 }
 
 pub struct TarEntry<'a> {
@@ -194,11 +368,157 @@ impl<'a> Read for TarEntry<'a> {
     }
 }
 
+/// Bookkeeping shared by [`TarReader`] and [`StreamTarReader`]; the two only
+/// differ in how they skip over bytes they don't need to read.
+struct ReaderState {
+    buf: TarBlock,
+    to_advance: usize,
+    /// PAX records from all global ('g') extended headers read so far,
+    /// applied to every entry read after them; a later global header
+    /// overrides only the keys it repeats.
+    global_pax: HashMap<String, String>,
+}
+
+impl ReaderState {
+    fn new() -> Self {
+        Self {
+            buf: [0u8; BLOCK_SIZE],
+            to_advance: 0,
+            global_pax: HashMap::new(),
+        }
+    }
+}
+
+/// Upper bound on the payload size of a GNU long name/link ('L'/'K') entry.
+///
+/// These only ever hold a single path, so anything beyond a typical
+/// filesystem's `PATH_MAX` isn't a legitimate value — just an attacker-
+/// controlled `size` field trying to force a huge allocation before the
+/// header is otherwise validated.
+const MAX_LONG_NAME_SIZE: usize = 4096;
+
+/// Upper bound on the payload size of a PAX extended header ('x'/'g') entry.
+///
+/// Real PAX headers are a handful of short `key=value` records; this is
+/// generous enough for any legitimate archive while still rejecting a
+/// maliciously huge `size` field.
+const MAX_PAX_HEADER_SIZE: usize = 1024 * 1024;
+
+/// Skip `state.to_advance` bytes using `skip`, then parse the next header,
+/// chasing any GNU long name/link or PAX extended header entries along the
+/// way. `skip` must consume exactly the requested number of bytes from
+/// `handle`, whether by seeking or by reading and discarding them.
+fn next_entry_impl<'a, T, S>(
+    handle: &'a mut T,
+    state: &'a mut ReaderState,
+    mut skip: S,
+) -> Result<TarEntry<'a>, TarError>
+    where T: Read, S: FnMut(&mut T, usize) -> io::Result<()> {
+    // GNU long name/link entries ('L'/'K') describe the *next* header; they
+    // may be chained, so keep consuming them until a real header turns up.
+    let mut long_name: Option<OsString> = None;
+    let mut long_link: Option<OsString> = None;
+    let mut local_pax: HashMap<String, String> = HashMap::new();
+
+    loop {
+        skip(handle, state.to_advance)?;
+
+        let read = handle.read(&mut state.buf)?;
+        if read != BLOCK_SIZE {
+            return Err(TarError::FileEnd);
+        }
+
+        if state.buf.iter().all(|&x| x == 0) {
+            // A block of all null bytes indicates that we are past the end of the tar file.
+            return Err(TarError::FileEnd);
+        }
+
+        let header = TarHeader::from_block(&state.buf)?;
+        let this_block_size = header.block_size().checked_mul(BLOCK_SIZE)
+            .ok_or(TarError::SizeOverflow)?;
+
+        if header.link == LinkType::Other('L') || header.link == LinkType::Other('K') {
+            if header.size > MAX_LONG_NAME_SIZE {
+                return Err(TarError::PayloadTooLarge);
+            }
+            let mut payload = vec![0u8; header.size];
+            handle.read_exact(&mut payload)?;
+            if let Some(pos) = payload.iter().position(|&b| b == 0) {
+                payload.truncate(pos);
+            }
+            // Only the padding up to the next block boundary remains to skip.
+            state.to_advance = this_block_size - header.size;
+
+            let value = OsString::from_vec(payload);
+            if header.link == LinkType::Other('L') {
+                long_name = Some(value);
+            } else {
+                long_link = Some(value);
+            }
+            continue;
+        }
+
+        if header.link == LinkType::Other('x') || header.link == LinkType::Other('g') {
+            if header.size > MAX_PAX_HEADER_SIZE {
+                return Err(TarError::PayloadTooLarge);
+            }
+            let mut payload = vec![0u8; header.size];
+            handle.read_exact(&mut payload)?;
+            // Only the padding up to the next block boundary remains to skip.
+            state.to_advance = this_block_size - header.size;
+
+            let records = parse_pax_records(&payload)?;
+            if header.link == LinkType::Other('g') {
+                state.global_pax.extend(records);
+            } else {
+                local_pax.extend(records);
+            }
+            continue;
+        }
+
+        break;
+    }
+
+    let mut header = TarHeader::from_block(&state.buf)?;
+    if let Some(name) = long_name.take() {
+        header.name = Cow::Owned(name);
+    }
+    if let Some(link_name) = long_link.take() {
+        header.link_name = Some(Cow::Owned(link_name));
+    }
+
+    let mut pax = state.global_pax.clone();
+    pax.extend(local_pax);
+    apply_pax_records(&mut header, &pax)?;
+
+    // Recomputed from `header.size` *after* PAX overrides are applied: a PAX
+    // `size` record can replace the on-disk octal field with an unrelated
+    // value, so the block count it implies must be derived from the final
+    // size, not the raw header's. `checked_mul` guards against a maliciously
+    // huge PAX `size` (e.g. near `usize::MAX`) overflowing when the block
+    // count it was just rounded up into is turned back into bytes.
+    state.to_advance = header.block_size().checked_mul(BLOCK_SIZE)
+        .ok_or(TarError::SizeOverflow)?;
+
+    Ok(TarEntry::new(header, handle, &mut state.to_advance))
+}
+
+/// Read and discard `amount` bytes from `handle` using a scratch buffer, for
+/// readers that cannot skip ahead with `Seek`.
+fn discard<T: Read>(handle: &mut T, mut amount: usize) -> io::Result<()> {
+    let mut scratch = [0u8; BLOCK_SIZE];
+    while amount > 0 {
+        let chunk = amount.min(scratch.len());
+        handle.read_exact(&mut scratch[..chunk])?;
+        amount -= chunk;
+    }
+    Ok(())
+}
+
 pub struct TarReader<T>
 where T: Read + Seek {
     handle: T,
-    buf: TarBlock,
-    to_advance: usize
+    state: ReaderState,
 }
 
 impl<T> TarReader<T>
@@ -207,36 +527,79 @@ where T: Read + Seek {
     pub fn new(handle: T) -> TarReader<T> {
         Self {
             handle,
-            buf: [0u8; 512],
-            to_advance: 0,
+            state: ReaderState::new(),
         }
     }
 
     pub fn next_entry(&mut self) -> Result<TarEntry, TarError> {
-        if self.to_advance > 0 {
-            self.handle.seek(SeekFrom::Current(self.to_advance as i64))?;
-        }
+        next_entry_impl(&mut self.handle, &mut self.state, |handle, amount| {
+            if amount > 0 {
+                handle.seek(SeekFrom::Current(amount as i64))?;
+            }
+            Ok(())
+        })
+    }
+}
 
-        let read = self.handle.read(&mut self.buf)?;
-        if read != BLOCK_SIZE {
-            return Err(TarError::FileEnd);
-        }
+/// A [`TarReader`] variant for sources that only implement [`Read`] (pipes,
+/// sockets, or decompressors like `flate2`), at the cost of reading through
+/// unused entry bodies and padding instead of seeking past them.
+pub struct StreamTarReader<T>
+where T: Read {
+    handle: T,
+    state: ReaderState,
+}
 
-        if self.buf.iter().all(|&x| x == 0) {
-            // A block of all null bytes indicates that we are past the end of the tar file.
-            return Err(TarError::FileEnd);
+impl<T> StreamTarReader<T>
+where T: Read {
+
+    pub fn new(handle: T) -> StreamTarReader<T> {
+        Self {
+            handle,
+            state: ReaderState::new(),
         }
+    }
 
-        let header = TarHeader::from_block(&self.buf)?;
-        self.to_advance = header.block_size() * BLOCK_SIZE;
+    pub fn next_entry(&mut self) -> Result<TarEntry, TarError> {
+        next_entry_impl(&mut self.handle, &mut self.state, discard)
+    }
+}
 
-        Ok(TarEntry::new(header, &mut self.handle, &mut self.to_advance))
+/// Apply parsed PAX extended header records on top of a regular header.
+fn apply_pax_records(header: &mut TarHeader, pax: &HashMap<String, String>) -> Result<(), TarError> {
+    if let Some(path) = pax.get("path") {
+        header.name = Cow::Owned(OsString::from(path.clone()));
+    }
+    if let Some(linkpath) = pax.get("linkpath") {
+        header.link_name = Some(Cow::Owned(OsString::from(linkpath.clone())));
+    }
+    if let Some(size) = pax.get("size") {
+        header.size = size.parse().map_err(|_| TarError::EncodingError)?;
+    }
+    if let Some(mtime) = pax.get("mtime") {
+        let mtime: f64 = mtime.parse().map_err(|_| TarError::EncodingError)?;
+        header.mtime = mtime as u64;
+    }
+    if let Some(uid) = pax.get("uid") {
+        header.owner = uid.parse().map_err(|_| TarError::EncodingError)?;
+    }
+    if let Some(gid) = pax.get("gid") {
+        header.group = gid.parse().map_err(|_| TarError::EncodingError)?;
     }
+    if let Some(uname) = pax.get("uname") {
+        header.owner_name = Some(Cow::Owned(OsString::from(uname.clone())));
+    }
+    if let Some(gname) = pax.get("gname") {
+        header.group_name = Some(Cow::Owned(OsString::from(gname.clone())));
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
+    use std::os::unix::ffi::OsStrExt;
 
     use super::*;
 
@@ -252,6 +615,30 @@ mod tests {
         assert_eq!(LinkType::Normal, header.link);
     }
 
+    #[test]
+    fn test_base256_size() {
+        let mut block = [0u8; 512];
+        block.copy_from_slice(&SAMPLE_DATA[..512]);
+        block[SIZE_RANGE].copy_from_slice(&[
+            0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00,
+        ]);
+
+        let header = TarHeader::from_v7_header(&block).unwrap();
+        assert_eq!(0x4000, header.size);
+    }
+
+    #[test]
+    fn test_ustar_prefix() {
+        let mut block = [0u8; 512];
+        block.copy_from_slice(&SAMPLE_DATA[..512]);
+        block[MAGIC_RANGE].copy_from_slice(b"ustar\0");
+        block[PREFIX_RANGE.start..PREFIX_RANGE.start + "some/prefix".len()]
+            .copy_from_slice(b"some/prefix");
+
+        let header = TarHeader::from_ustar_header(&block).unwrap();
+        assert_eq!("some/prefix/Cargo.toml", header.name.to_str().unwrap());
+    }
+
     #[test]
     fn test_checksum() {
         let mut block = [0u8; 512];
@@ -279,4 +666,297 @@ mod tests {
         let err = entry.err().unwrap();
         assert_eq!(TarError::FileEnd, err);
     }
+
+    #[test]
+    fn test_stream_reader() {
+        // A plain byte slice only implements `Read`, not `Seek`.
+        let mut reader = StreamTarReader::new(SAMPLE_DATA);
+        {
+            let entry = reader.next_entry().unwrap();
+            let header = entry.get_header();
+            assert_eq!("Cargo.toml", header.name.to_str().unwrap());
+            // Deliberately don't read the body, to exercise the drain on the
+            // next `next_entry` call.
+        }
+
+        let entry = reader.next_entry();
+        let err = entry.err().unwrap();
+        assert_eq!(TarError::FileEnd, err);
+    }
+
+    #[test]
+    fn test_safe_path_accepts_normal_name() {
+        let mut block = [0u8; 512];
+        block.copy_from_slice(&SAMPLE_DATA[..512]);
+
+        let header = TarHeader::from_v7_header(&block).unwrap();
+        let path = header.safe_path("/extract/here").unwrap();
+        assert_eq!(Path::new("/extract/here/Cargo.toml"), path);
+    }
+
+    #[test]
+    fn test_safe_path_rejects_absolute_name() {
+        let block = make_header_block(b"/etc/passwd", b'0', 0);
+        let header = TarHeader::from_v7_header(&block).unwrap();
+
+        assert_eq!(Err(TarError::UnsafePath), header.safe_path("/extract/here"));
+    }
+
+    #[test]
+    fn test_safe_path_rejects_traversal() {
+        let block = make_header_block(b"../../etc/passwd", b'0', 0);
+        let header = TarHeader::from_v7_header(&block).unwrap();
+
+        assert_eq!(Err(TarError::UnsafePath), header.safe_path("/extract/here"));
+    }
+
+    #[test]
+    fn test_safe_path_rejects_escaping_symlink_target() {
+        let mut block = make_header_block(b"link", b'2', 0);
+        block[LINK_NAME_RANGE][.."../../../etc/passwd".len()]
+            .copy_from_slice(b"../../../etc/passwd");
+
+        let header = TarHeader::from_v7_header(&block).unwrap();
+        assert_eq!(Err(TarError::UnsafePath), header.safe_path("/extract/here"));
+    }
+
+    #[test]
+    fn test_safe_path_accepts_symlink_target_relative_to_entry_dir() {
+        // `../../../x` from within `a/b/c/d/` stays inside `root`
+        // (`root/a/x`), even though the same target resolved against `root`
+        // itself would escape it.
+        let mut block = make_header_block(b"a/b/c/d/link", b'2', 0);
+        block[LINK_NAME_RANGE][.."../../../x".len()]
+            .copy_from_slice(b"../../../x");
+
+        let header = TarHeader::from_v7_header(&block).unwrap();
+        let path = header.safe_path("/extract/here").unwrap();
+        assert_eq!(Path::new("/extract/here/a/b/c/d/link"), path);
+    }
+
+    #[test]
+    fn test_safe_path_rejects_symlink_target_escaping_via_entry_dir() {
+        // Only 1 level deep (`a/`), so a single `..` component already
+        // reaches `root` and a 2nd escapes it.
+        let mut block = make_header_block(b"a/link", b'2', 0);
+        block[LINK_NAME_RANGE][.."../../../etc/passwd".len()]
+            .copy_from_slice(b"../../../etc/passwd");
+
+        let header = TarHeader::from_v7_header(&block).unwrap();
+        assert_eq!(Err(TarError::UnsafePath), header.safe_path("/extract/here"));
+    }
+
+    /// Create a fresh, empty directory under the system temp dir for a test
+    /// to extract into, returning it alongside a sibling directory outside
+    /// of it that a malicious symlink might redirect into.
+    fn make_extraction_root(name: &str) -> (PathBuf, PathBuf) {
+        let base = std::env::temp_dir().join(format!("rstar-test-{}-{}", std::process::id(), name));
+        let root = base.join("root");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        (root, outside)
+    }
+
+    #[test]
+    fn test_safe_path_rejects_symlink_redirection_on_disk() {
+        let (root, outside) = make_extraction_root("redirect");
+
+        // A previous entry in the archive already extracted `foo` as a
+        // symlink pointing outside `root`.
+        std::os::unix::fs::symlink(&outside, root.join("foo")).unwrap();
+
+        // This entry's name is lexically innocent - no `..` anywhere - but
+        // resolves outside `root` once `foo` is followed on disk.
+        let block = make_header_block(b"foo/passwd", b'0', 0);
+        let header = TarHeader::from_v7_header(&block).unwrap();
+
+        let result = header.safe_path(&root);
+        std::fs::remove_dir_all(root.parent().unwrap()).ok();
+
+        assert_eq!(Err(TarError::UnsafePath), result);
+    }
+
+    #[test]
+    fn test_safe_path_accepts_existing_real_directory() {
+        let (root, _outside) = make_extraction_root("benign");
+
+        // A previous entry already created a real (non-symlink) directory;
+        // extracting into it should still be accepted.
+        std::fs::create_dir_all(root.join("foo")).unwrap();
+
+        let block = make_header_block(b"foo/passwd", b'0', 0);
+        let header = TarHeader::from_v7_header(&block).unwrap();
+
+        let result = header.safe_path(&root);
+        std::fs::remove_dir_all(root.parent().unwrap()).ok();
+
+        assert_eq!(root.join("foo/passwd"), result.unwrap());
+    }
+
+    /// Write an octal value into a header field, as `from_v7_header` expects.
+    fn set_octal(block: &mut TarBlock, range: std::ops::Range<usize>, value: u64) {
+        let digits = format!("{:o}", value);
+        let field = &mut block[range];
+        for b in field.iter_mut() {
+            *b = 0;
+        }
+        field[..digits.len()].copy_from_slice(digits.as_bytes());
+    }
+
+    /// Build a valid, checksummed header block for a regular file.
+    fn make_header_block(name: &[u8], link_type: u8, size: usize) -> TarBlock {
+        let mut block = [0u8; BLOCK_SIZE];
+        block[NAME_RANGE][..name.len()].copy_from_slice(name);
+        set_octal(&mut block, MODE_RANGE, 0o644);
+        set_octal(&mut block, OWNER_RANGE, 0);
+        set_octal(&mut block, GROUP_RANGE, 0);
+        set_octal(&mut block, MTIME_RANGE, 0);
+        block[LINK_TYPE_OFFSET] = link_type;
+        set_octal(&mut block, SIZE_RANGE, size as u64);
+
+        for b in block[CHECKSUM_RANGE].iter_mut() {
+            *b = b' ';
+        }
+        let checksum = compute_checksum(&block);
+        set_octal(&mut block, CHECKSUM_RANGE, checksum as u64);
+
+        block
+    }
+
+    #[test]
+    fn test_gnu_long_name() {
+        let long_name = b"a/path/long/enough/to/require/the/gnu/long/name/extension/short.txt";
+
+        let long_name_header = make_header_block(b"./@LongLink", b'L', long_name.len() + 1);
+        let mut payload = long_name.to_vec();
+        payload.push(0);
+        payload.resize(BLOCK_SIZE, 0);
+
+        let real_header = make_header_block(b"short.txt", b'0', 0);
+
+        let mut archive = Vec::new();
+        archive.extend_from_slice(&long_name_header);
+        archive.extend_from_slice(&payload);
+        archive.extend_from_slice(&real_header);
+        archive.extend_from_slice(&[0u8; BLOCK_SIZE * 2]);
+
+        let mut reader = TarReader::new(Cursor::new(archive));
+        let entry = reader.next_entry().unwrap();
+        let header = entry.get_header();
+        assert_eq!(long_name, header.name.as_bytes());
+    }
+
+    #[test]
+    fn test_pax_overrides_name_and_size() {
+        let content = b"hello world";
+        // The on-disk `size` field is left at 0; the real size only exists
+        // in the PAX record, which is the realistic case this override
+        // exists for (archives commonly put a placeholder in the octal
+        // field once the true size doesn't fit it).
+        let records = b"23 path=overridden.txt\n11 size=11\n";
+
+        let pax_header = make_header_block(b"PaxHeaders/entry", b'x', records.len());
+        let mut payload = records.to_vec();
+        payload.resize(BLOCK_SIZE, 0);
+
+        let real_header = make_header_block(b"short.txt", b'0', 0);
+        let mut body = content.to_vec();
+        body.resize(BLOCK_SIZE, 0);
+
+        let mut archive = Vec::new();
+        archive.extend_from_slice(&pax_header);
+        archive.extend_from_slice(&payload);
+        archive.extend_from_slice(&real_header);
+        archive.extend_from_slice(&body);
+        archive.extend_from_slice(&[0u8; BLOCK_SIZE * 2]);
+
+        let mut reader = TarReader::new(Cursor::new(archive));
+        let mut entry = reader.next_entry().unwrap();
+        assert_eq!("overridden.txt", entry.get_header().name.to_str().unwrap());
+        assert_eq!(content.len(), entry.get_header().size);
+
+        let mut buf = Vec::new();
+        entry.read_to_end(&mut buf).unwrap();
+        assert_eq!(content, buf.as_slice());
+    }
+
+    #[test]
+    fn test_pax_size_overflow_returns_error_instead_of_panicking() {
+        let record = b"29 size=18446744073709551615\n";
+
+        let pax_header = make_header_block(b"PaxHeaders/entry", b'x', record.len());
+        let mut payload = record.to_vec();
+        payload.resize(BLOCK_SIZE, 0);
+
+        let real_header = make_header_block(b"short.txt", b'0', 0);
+
+        let mut archive = Vec::new();
+        archive.extend_from_slice(&pax_header);
+        archive.extend_from_slice(&payload);
+        archive.extend_from_slice(&real_header);
+        archive.extend_from_slice(&[0u8; BLOCK_SIZE * 2]);
+
+        let mut reader = TarReader::new(Cursor::new(archive));
+        let err = reader.next_entry().err().unwrap();
+        assert_eq!(TarError::SizeOverflow, err);
+    }
+
+    #[test]
+    fn test_pax_overrides_mtime_and_ownership() {
+        let records = b"20 mtime=1577836800\n12 uid=4242\n12 gid=4343\n15 uname=alice\n15 gname=staff\n";
+
+        let pax_header = make_header_block(b"PaxHeaders/entry", b'x', records.len());
+        let mut payload = records.to_vec();
+        payload.resize(BLOCK_SIZE, 0);
+
+        let real_header = make_header_block(b"short.txt", b'0', 0);
+
+        let mut archive = Vec::new();
+        archive.extend_from_slice(&pax_header);
+        archive.extend_from_slice(&payload);
+        archive.extend_from_slice(&real_header);
+        archive.extend_from_slice(&[0u8; BLOCK_SIZE * 2]);
+
+        let mut reader = TarReader::new(Cursor::new(archive));
+        let entry = reader.next_entry().unwrap();
+        let header = entry.get_header();
+        assert_eq!(1577836800, header.mtime);
+        assert_eq!(4242, header.owner);
+        assert_eq!(4343, header.group);
+        assert_eq!("alice", header.owner_name.as_ref().unwrap().to_str().unwrap());
+        assert_eq!("staff", header.group_name.as_ref().unwrap().to_str().unwrap());
+    }
+
+    #[test]
+    fn test_pax_global_header_persists_across_entries() {
+        let global_record = b"15 uname=alice\n";
+        let global_header = make_header_block(b"PaxHeaders/global", b'g', global_record.len());
+        let mut global_payload = global_record.to_vec();
+        global_payload.resize(BLOCK_SIZE, 0);
+
+        let first_header = make_header_block(b"first.txt", b'0', 0);
+        let second_header = make_header_block(b"second.txt", b'0', 0);
+
+        let mut archive = Vec::new();
+        archive.extend_from_slice(&global_header);
+        archive.extend_from_slice(&global_payload);
+        archive.extend_from_slice(&first_header);
+        archive.extend_from_slice(&second_header);
+        archive.extend_from_slice(&[0u8; BLOCK_SIZE * 2]);
+
+        let mut reader = TarReader::new(Cursor::new(archive));
+
+        let first = reader.next_entry().unwrap();
+        assert_eq!(
+            "alice",
+            first.get_header().owner_name.as_ref().unwrap().to_str().unwrap()
+        );
+
+        let second = reader.next_entry().unwrap();
+        assert_eq!(
+            "alice",
+            second.get_header().owner_name.as_ref().unwrap().to_str().unwrap()
+        );
+    }
 }
@@ -1,13 +1,13 @@
 use std::ffi::OsStr;
-use std::num::ParseIntError;
 use std::os::unix::ffi::OsStrExt;
 use std::str;
 use std::usize;
 
 use itertools::Itertools;
-use num::Num;
+use num::{Num, NumCast};
 
 use crate::constants::header::CHECKSUM_RANGE;
+use crate::TarError;
 
 /// Create an &str from a null-terminated string.
 ///
@@ -40,10 +40,72 @@ pub fn parse_octal<T>(size: &[u8]) -> Result<T, T::FromStrRadixErr>
     T::from_str_radix(trimmed_str(size).unwrap_or(""), 8)
 }
 
-pub fn parse_size(size: &[u8]) -> Result<usize, ParseIntError> {
+/// Decode a GNU/STAR base-256 (binary) encoded numeric field.
+///
+/// A base-256 field is distinguished from an octal one by the top bit of its
+/// first byte being set. The remaining bits encode a big-endian integer,
+/// unless the first byte is `0xFF`, in which case the whole field is a
+/// two's-complement negative number instead.
+fn parse_base256(bytes: &[u8]) -> i64 {
+    if bytes[0] == 0xFF {
+        bytes.iter().fold(-1i64, |acc, &b| (acc << 8) | b as i64)
+    } else {
+        let masked_first = bytes[0] & 0x7F;
+        std::iter::once(masked_first)
+            .chain(bytes[1..].iter().cloned())
+            .fold(0i64, |acc, b| (acc << 8) | b as i64)
+    }
+}
+
+/// Parse a numeric header field, transparently handling the GNU/STAR
+/// base-256 extension.
+///
+/// Fields are normally stored as null-terminated octal strings, but if the
+/// top bit of the first byte is set the field is instead binary (base-256)
+/// encoded, which is required to represent values that don't fit in the
+/// fixed-width octal field (e.g. file sizes over 8 GiB).
+pub fn parse_numeric<T>(bytes: &[u8]) -> Result<T, TarError>
+    where T: Num + NumCast, TarError: From<T::FromStrRadixErr> {
+    if bytes[0] & 0x80 != 0 {
+        NumCast::from(parse_base256(bytes)).ok_or(TarError::FieldOutOfRange)
+    } else {
+        Ok(parse_octal(bytes)?)
+    }
+}
+
+pub fn parse_size(size: &[u8]) -> Result<usize, TarError> {
     debug_assert!(size.len() == 12);
-    // TODO: implement the extension format.
-    parse_octal(size)
+    parse_numeric(size)
+}
+
+/// Parse the records of a PAX extended header payload.
+///
+/// Each record is encoded as `"<len> <key>=<value>\n"`, where `<len>` is the
+/// decimal length of the whole record, including the length field, the
+/// space and the trailing newline.
+pub fn parse_pax_records(mut data: &[u8]) -> Result<Vec<(String, String)>, TarError> {
+    let mut records = Vec::new();
+
+    while !data.is_empty() {
+        let space = data.iter().position(|&b| b == b' ').ok_or(TarError::EncodingError)?;
+        let len: usize = str::from_utf8(&data[..space])
+            .map_err(|_| TarError::EncodingError)?
+            .parse()
+            .map_err(|_| TarError::EncodingError)?;
+
+        if len <= space + 1 || len > data.len() {
+            return Err(TarError::EncodingError);
+        }
+
+        let record = str::from_utf8(&data[space + 1..len - 1])
+            .map_err(|_| TarError::EncodingError)?;
+        let (key, value) = record.split_once('=').ok_or(TarError::EncodingError)?;
+        records.push((key.to_string(), value.to_string()));
+
+        data = &data[len..];
+    }
+
+    Ok(records)
 }
 
 /// Compute the checksum for a given block.